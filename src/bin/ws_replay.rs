@@ -0,0 +1,308 @@
+// =============================================================================
+// 🎞️ ws_replay —— 回放 start_recording 录制的流量
+//
+// 用法：
+//   ws_replay client <录制文件> <ws://地址> [--speed 倍速] [--client-id ID] [--side server|client]
+//       作为客户端连接服务器，按原始时间间隔重放“客户端 → 服务端”方向的帧
+//   ws_replay server <录制文件> <监听地址> [--speed 倍速] [--client-id ID] [--side server|client]
+//       作为假服务端监听，每个连接上来的客户端都会按原始时间间隔收到“服务端 → 客户端”方向的帧
+//
+// 两种模式都会打印对端发来的消息。--client-id 只回放服务端录制中指定客户端的帧，
+// 未指定时默认回放录制中第一个出现的客户端。
+// 同一进程同时录制了服务端与客户端时，同一帧会出现两次，默认取服务端视角，可用 --side 指定。
+// 明文模式的录制不含加密信封，回放目标需关闭加密；线上模式的录制可能被对端的防重放校验拒绝。
+// 密钥协商与会话标识帧（type 为 kx / session）每个连接都不同，只录制不回放。
+// =============================================================================
+
+use base64::{Engine as _, engine::general_purpose};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::io::BufRead;
+use tokio::time::{sleep_until, Duration, Instant};
+use tungstenite::Message;
+
+/// 录制文件中的一帧（格式见 lib.rs「流量录制」）
+#[derive(Deserialize, Clone)]
+struct RecordedFrame {
+    ts: i64,
+    side: String,
+    direction: String,
+    client_id: Option<u64>,
+    opcode: String,
+    payload: String,
+    #[serde(default)]
+    base64: bool,
+}
+
+impl RecordedFrame {
+    /// 是否为客户端发往服务端的帧
+    fn is_client_to_server(&self) -> bool {
+        matches!((self.side.as_str(), self.direction.as_str()), ("server", "in") | ("client", "out"))
+    }
+
+    /// 是否为连接建立时的密钥协商 / 会话标识帧
+    fn is_handshake(&self) -> bool {
+        serde_json::from_str::<serde_json::Value>(&self.payload)
+            .ok()
+            .and_then(|value| value.get("type")?.as_str().map(|t| matches!(t, "kx" | "session")))
+            .unwrap_or(false)
+    }
+
+    fn to_message(&self) -> Option<Message> {
+        match self.opcode.as_str() {
+            "text" if self.is_handshake() => None,
+            "text" => Some(Message::Text(self.payload.clone().into())),
+            "binary" => {
+                let data = if self.base64 {
+                    general_purpose::STANDARD.decode(&self.payload).ok()?
+                } else {
+                    self.payload.clone().into_bytes()
+                };
+                Some(Message::Binary(data.into()))
+            }
+            // 控制帧由 WebSocket 协议栈自动处理，不回放
+            _ => None,
+        }
+    }
+}
+
+struct Options {
+    mode: String,
+    recording: String,
+    target: String,
+    speed: f64,
+    client_id: Option<u64>,
+    side: Option<String>,
+}
+
+fn usage() -> ! {
+    eprintln!("用法：");
+    eprintln!("  ws_replay client <录制文件> <ws://地址> [--speed 倍速] [--client-id ID] [--side server|client]");
+    eprintln!("  ws_replay server <录制文件> <监听地址> [--speed 倍速] [--client-id ID] [--side server|client]");
+    std::process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 3 || !matches!(args[0].as_str(), "client" | "server") {
+        usage();
+    }
+    let mut options = Options {
+        mode: args[0].clone(),
+        recording: args[1].clone(),
+        target: args[2].clone(),
+        speed: 1.0,
+        client_id: None,
+        side: None,
+    };
+    let mut rest = args[3..].iter();
+    while let Some(flag) = rest.next() {
+        let value = rest.next().unwrap_or_else(|| usage());
+        match flag.as_str() {
+            "--speed" => options.speed = value.parse().ok().filter(|s: &f64| *s > 0.0).unwrap_or_else(|| usage()),
+            "--client-id" => options.client_id = Some(value.parse().unwrap_or_else(|_| usage())),
+            "--side" if matches!(value.as_str(), "server" | "client") => options.side = Some(value.clone()),
+            _ => usage(),
+        }
+    }
+    options
+}
+
+/// 读取录制文件，返回指定方向的可回放帧
+fn load_frames(options: &Options, client_to_server: bool) -> std::io::Result<Vec<RecordedFrame>> {
+    let file = std::fs::File::open(&options.recording)?;
+    let frames: Vec<RecordedFrame> = std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<RecordedFrame>(&line).ok())
+        .filter(|frame| frame.is_client_to_server() == client_to_server)
+        .filter(|frame| frame.to_message().is_some())
+        .collect();
+
+    // 未指定视角时：录制中有服务端视角的帧则取服务端视角，否则取客户端视角
+    let side = options.side.clone().unwrap_or_else(|| {
+        let has_server = frames.iter().any(|frame| frame.side == "server");
+        if has_server { "server" } else { "client" }.to_string()
+    });
+    let frames: Vec<RecordedFrame> = frames.into_iter().filter(|frame| frame.side == side).collect();
+    if side == "client" {
+        return Ok(frames);
+    }
+
+    // 服务端录制含多个客户端的帧：未指定 --client-id 时只回放第一个录制到的客户端，避免把多个连接的帧混成一条流
+    let client_id = options.client_id.or_else(|| {
+        let first = frames.iter().find_map(|frame| frame.client_id)?;
+        if frames.iter().any(|frame| frame.client_id.is_some_and(|id| id != first)) {
+            println!("ℹ️ 录制中有多个客户端，默认回放客户端 {} 的帧，可用 --client-id 指定", first);
+        }
+        Some(first)
+    });
+    Ok(frames.into_iter().filter(|frame| frame.client_id == client_id).collect())
+}
+
+/// 按原始时间间隔（除以倍速）依次发送帧
+async fn replay_frames<S>(sink: &mut S, frames: &[RecordedFrame], speed: f64) -> Result<(), tungstenite::Error>
+where
+    S: futures_util::Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    let first_ts = match frames.first() {
+        Some(frame) => frame.ts,
+        None => return Ok(()),
+    };
+    let started = Instant::now();
+    for frame in frames {
+        let offset = (frame.ts - first_ts).max(0) as f64 / speed;
+        sleep_until(started + Duration::from_millis(offset as u64)).await;
+        if let Some(message) = frame.to_message() {
+            sink.send(message).await?;
+        }
+    }
+    Ok(())
+}
+
+fn print_received(peer: &str, message: &Message) {
+    match message {
+        Message::Text(text) => println!("⬅️ {} 文本: {}", peer, text.as_str()),
+        Message::Binary(data) => println!("⬅️ {} 二进制: {} 字节", peer, data.len()),
+        Message::Close(frame) => println!("⬅️ {} 关闭: {:?}", peer, frame),
+        _ => {}
+    }
+}
+
+async fn run_client(options: Options) -> Result<(), String> {
+    let frames = load_frames(&options, true).map_err(|e| format!("读取录制文件失败: {}", e))?;
+    println!("▶️ 向 {} 回放 {} 帧", options.target, frames.len());
+
+    let (ws_stream, _) = tokio_tungstenite::connect_async(options.target.as_str())
+        .await
+        .map_err(|e| format!("连接失败: {}", e))?;
+    let (mut write, mut read) = ws_stream.split();
+    let reader = tokio::spawn(async move {
+        while let Some(Ok(message)) = read.next().await {
+            print_received("服务端", &message);
+        }
+    });
+
+    replay_frames(&mut write, &frames, options.speed).await.map_err(|e| format!("发送失败: {}", e))?;
+    // 留出时间接收最后的应答
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let _ = write.send(Message::Close(None)).await;
+    let _ = tokio::time::timeout(Duration::from_secs(2), reader).await;
+    println!("✅ 回放完成");
+    Ok(())
+}
+
+async fn run_server(options: Options) -> Result<(), String> {
+    let frames = load_frames(&options, false).map_err(|e| format!("读取录制文件失败: {}", e))?;
+    let listener = tokio::net::TcpListener::bind(&options.target)
+        .await
+        .map_err(|e| format!("绑定 {} 失败: {}", options.target, e))?;
+    println!("▶️ 假服务端监听 {}，每个连接回放 {} 帧", options.target, frames.len());
+
+    loop {
+        let (stream, peer) = listener.accept().await.map_err(|e| format!("接受连接失败: {}", e))?;
+        let frames = frames.clone();
+        let speed = options.speed;
+        tokio::spawn(async move {
+            let ws_stream = match tokio_tungstenite::accept_async(stream).await {
+                Ok(ws) => ws,
+                Err(e) => {
+                    eprintln!("❌ {} 握手失败: {}", peer, e);
+                    return;
+                }
+            };
+            println!("🔌 {} 已连接", peer);
+            let (mut write, mut read) = ws_stream.split();
+            let peer_name = peer.to_string();
+            tokio::spawn(async move {
+                while let Some(Ok(message)) = read.next().await {
+                    print_received(&peer_name, &message);
+                }
+            });
+            match replay_frames(&mut write, &frames, speed).await {
+                Ok(()) => println!("✅ {} 回放完成", peer),
+                Err(e) => eprintln!("❌ {} 发送失败: {}", peer, e),
+            }
+        });
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = parse_options();
+    let result = match options.mode.as_str() {
+        "client" => run_client(options).await,
+        _ => run_server(options).await,
+    };
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = concat!(
+        r#"{"ts":1,"side":"server","direction":"in","client_id":1,"opcode":"text","payload":"{\"type\":\"kx\"}"}"#, "\n",
+        r#"{"ts":2,"side":"server","direction":"in","client_id":1,"opcode":"text","payload":"a1"}"#, "\n",
+        r#"{"ts":3,"side":"server","direction":"out","client_id":1,"opcode":"text","payload":"r1"}"#, "\n",
+        r#"{"ts":4,"side":"server","direction":"in","client_id":2,"opcode":"text","payload":"b1"}"#, "\n",
+        r#"{"ts":5,"side":"server","direction":"out","client_id":2,"opcode":"binary","payload":"cjI=","base64":true}"#, "\n",
+        r#"{"ts":6,"side":"server","direction":"out","client_id":1,"opcode":"ping","payload":""}"#, "\n",
+        r#"{"ts":7,"side":"client","direction":"out","opcode":"text","payload":"a1"}"#, "\n",
+        r#"{"ts":8,"side":"client","direction":"in","opcode":"text","payload":"r1"}"#, "\n",
+        "not json\n",
+    );
+
+    fn options(name: &str, client_id: Option<u64>, side: Option<&str>) -> Options {
+        let path = std::env::temp_dir().join(format!("ws_replay_test_{}_{}.jsonl", name, std::process::id()));
+        std::fs::write(&path, FIXTURE).unwrap();
+        Options {
+            mode: "server".to_string(),
+            recording: path.to_string_lossy().into_owned(),
+            target: String::new(),
+            speed: 1.0,
+            client_id,
+            side: side.map(str::to_string),
+        }
+    }
+
+    fn payloads(options: &Options, client_to_server: bool) -> Vec<String> {
+        let frames = load_frames(options, client_to_server).unwrap();
+        let _ = std::fs::remove_file(&options.recording);
+        frames.into_iter().map(|frame| frame.payload).collect()
+    }
+
+    #[test]
+    fn direction_depends_on_recording_side() {
+        let frame = |side: &str, direction: &str| RecordedFrame {
+            ts: 0,
+            side: side.to_string(),
+            direction: direction.to_string(),
+            client_id: None,
+            opcode: "text".to_string(),
+            payload: String::new(),
+            base64: false,
+        };
+        assert!(frame("server", "in").is_client_to_server());
+        assert!(frame("client", "out").is_client_to_server());
+        assert!(!frame("server", "out").is_client_to_server());
+        assert!(!frame("client", "in").is_client_to_server());
+    }
+
+    #[test]
+    fn server_recording_defaults_to_first_client() {
+        assert_eq!(payloads(&options("default_out", None, None), false), ["r1"]);
+        assert_eq!(payloads(&options("default_in", None, None), true), ["a1"]);
+        assert_eq!(payloads(&options("client2", Some(2), None), false), ["cjI="]);
+        assert!(payloads(&options("missing", Some(9), None), false).is_empty());
+    }
+
+    #[test]
+    fn client_side_skips_handshake_and_control_frames() {
+        assert_eq!(payloads(&options("client_side_out", None, Some("client")), true), ["a1"]);
+        assert_eq!(payloads(&options("client_side_in", Some(2), Some("client")), false), ["r1"]);
+    }
+}